@@ -0,0 +1,129 @@
+use crossterm::event::KeyCode;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+
+use crate::App;
+
+/// A named operation that can be bound to a key. Kept as a plain function
+/// pointer (rather than a trait object) since every action is just one of
+/// `App`'s existing `&mut self` methods.
+pub type Action = fn(&mut App);
+
+/// Builds the registry of action name -> action. This is the full set of
+/// operations that a keymap entry may refer to.
+pub fn load_actions() -> HashMap<&'static str, Action> {
+    let mut actions: HashMap<&'static str, Action> = HashMap::new();
+    actions.insert("quit", App::quit_guarded as Action);
+    actions.insert("select_first", App::select_first as Action);
+    actions.insert("select_last", App::select_last as Action);
+    actions.insert("select_next", App::select_next as Action);
+    actions.insert("select_prev", App::select_prev as Action);
+    actions.insert("complete_selected", App::complete_selected as Action);
+    actions.insert("delete_selected", App::delete_selected as Action);
+    actions.insert("insert_task", App::insert_task as Action);
+    actions.insert("append_task", App::append_task as Action);
+    actions.insert("edit_task", App::edit_task as Action);
+    actions.insert("enter_command", App::enter_command as Action);
+    actions
+}
+
+/// Per-mode key -> action name bindings, as loaded from the user's
+/// `keymap.toml`. Modes that aren't present in the config file keep their
+/// built-in defaults.
+#[derive(Debug, Default, Deserialize)]
+pub struct Keymap {
+    #[serde(default)]
+    pub view: HashMap<String, String>,
+    #[serde(default)]
+    pub select: HashMap<String, String>,
+}
+
+fn default_view_keymap() -> HashMap<String, String> {
+    HashMap::from(
+        [
+            ("Esc", "quit"),
+            ("q", "quit"),
+            ("a", "append_task"),
+            ("g", "select_first"),
+            ("Home", "select_first"),
+            ("G", "select_last"),
+            ("End", "select_last"),
+            ("j", "select_next"),
+            ("Down", "select_next"),
+            ("k", "select_prev"),
+            ("Up", "select_prev"),
+            (":", "enter_command"),
+        ]
+        .map(|(k, v)| (k.to_string(), v.to_string())),
+    )
+}
+
+fn default_select_keymap() -> HashMap<String, String> {
+    HashMap::from(
+        [
+            ("g", "select_first"),
+            ("Home", "select_first"),
+            ("G", "select_last"),
+            ("End", "select_last"),
+            ("j", "select_next"),
+            ("Down", "select_next"),
+            ("k", "select_prev"),
+            ("Up", "select_prev"),
+            (" ", "complete_selected"),
+            ("Enter", "complete_selected"),
+            ("x", "delete_selected"),
+            ("Delete", "delete_selected"),
+            ("i", "insert_task"),
+            ("a", "append_task"),
+            ("c", "edit_task"),
+            ("e", "edit_task"),
+            ("q", "quit"),
+            (":", "enter_command"),
+        ]
+        .map(|(k, v)| (k.to_string(), v.to_string())),
+    )
+}
+
+/// Loads `keymap.toml` from the user's config dir (e.g.
+/// `~/.config/tplan/keymap.toml`), merging it over the built-in defaults so a
+/// user only has to specify the bindings they want to change.
+pub fn load_keymap() -> Keymap {
+    let mut view = default_view_keymap();
+    let mut select = default_select_keymap();
+
+    if let Some(user) = read_user_keymap() {
+        view.extend(user.view);
+        select.extend(user.select);
+    }
+
+    Keymap { view, select }
+}
+
+fn read_user_keymap() -> Option<Keymap> {
+    let mut path = dirs::config_dir()?;
+    path.push("tplan");
+    path.push("keymap.toml");
+    let contents = fs::read_to_string(path).ok()?;
+    toml::from_str(&contents).ok()
+}
+
+/// Renders a pressed key the same way bindings are written in `keymap.toml`,
+/// e.g. `KeyCode::Char('j')` -> `"j"`, `KeyCode::Esc` -> `"Esc"`.
+pub fn key_code_to_string(code: KeyCode) -> Option<String> {
+    Some(match code {
+        KeyCode::Char(c) => c.to_string(),
+        KeyCode::Esc => "Esc".to_string(),
+        KeyCode::Enter => "Enter".to_string(),
+        KeyCode::Backspace => "Backspace".to_string(),
+        KeyCode::Delete => "Delete".to_string(),
+        KeyCode::Left => "Left".to_string(),
+        KeyCode::Right => "Right".to_string(),
+        KeyCode::Up => "Up".to_string(),
+        KeyCode::Down => "Down".to_string(),
+        KeyCode::Home => "Home".to_string(),
+        KeyCode::End => "End".to_string(),
+        KeyCode::Tab => "Tab".to_string(),
+        _ => return None,
+    })
+}
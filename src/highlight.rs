@@ -0,0 +1,134 @@
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+};
+use tplan::{parse_tag, Task};
+
+/// Builds a styled `Line` for one task: `+project` and `@context` tokens get
+/// their own hue, the `(priority)` marker is bold, and `key:value` tags are
+/// dimmed. This is the todo.txt analogue of syntax highlighting — it reads
+/// `task.summary` word by word rather than reformatting from the structured
+/// fields, so word order stays exactly as typed (runs of whitespace between
+/// words are drawn as a single space).
+pub fn highlight_task(task: &Task) -> Line<'static> {
+    let mut spans = Vec::new();
+
+    if let Some(priority) = task.priority {
+        spans.push(Span::styled(
+            format!("({priority}) "),
+            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+        ));
+    }
+
+    // Dates lead the summary in `Task::to_string()`'s output (completion
+    // date, then creation date); render them the same way here so a task
+    // with a date doesn't silently look identical to one without.
+    if let Some(completed_date) = task.completed_date {
+        spans.push(Span::styled(
+            format!("{} ", completed_date.to_string()),
+            Style::default().add_modifier(Modifier::DIM),
+        ));
+    }
+    if let Some(created) = task.created {
+        spans.push(Span::styled(
+            format!("{} ", created.to_string()),
+            Style::default().add_modifier(Modifier::DIM),
+        ));
+    }
+
+    for (i, word) in task.summary.split_whitespace().enumerate() {
+        if i > 0 {
+            spans.push(Span::raw(" "));
+        }
+        let style = word_style(word);
+        spans.push(Span::styled(word.to_string(), style));
+    }
+
+    if task.completed {
+        let completed = Style::default()
+            .add_modifier(Modifier::DIM)
+            .add_modifier(Modifier::ITALIC)
+            .add_modifier(Modifier::CROSSED_OUT);
+        spans = spans
+            .into_iter()
+            .map(|span| Span::styled(span.content, span.style.patch(completed)))
+            .collect();
+    }
+
+    Line::from(spans)
+}
+
+fn word_style(word: &str) -> Style {
+    if word.starts_with('+') && word.len() > 1 {
+        Style::default().fg(Color::Yellow)
+    } else if word.starts_with('@') && word.len() > 1 {
+        Style::default().fg(Color::Cyan)
+    } else if parse_tag(word).is_some() {
+        Style::default().add_modifier(Modifier::DIM)
+    } else {
+        Style::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tplan::Date;
+
+    #[test]
+    fn projects_and_contexts_get_colored() {
+        assert_eq!(word_style("+Family").fg, Some(Color::Yellow));
+        assert_eq!(word_style("@phone").fg, Some(Color::Cyan));
+    }
+
+    #[test]
+    fn tags_are_dimmed() {
+        assert!(word_style("due:2024-01-01")
+            .add_modifier
+            .contains(Modifier::DIM));
+    }
+
+    #[test]
+    fn urls_and_plain_words_are_not_specially_styled() {
+        // A lone `+`/`@` or a URL that `parse_tag` rejects shouldn't pick up
+        // any of the above styling.
+        assert_eq!(word_style("+"), Style::default());
+        assert_eq!(word_style("http://example.com"), Style::default());
+        assert_eq!(word_style("milk"), Style::default());
+    }
+
+    fn spans_text(line: &Line) -> String {
+        line.spans.iter().map(|s| s.content.as_ref()).collect()
+    }
+
+    #[test]
+    fn highlight_task_renders_dates_before_the_summary() {
+        let mut task = Task::default();
+        task.created = Some(Date {
+            year: 2024,
+            month: 1,
+            day: 2,
+        });
+        task.summary = "Call mom".to_string();
+        let line = highlight_task(&task);
+        assert_eq!(spans_text(&line), "2024-01-02 Call mom");
+    }
+
+    #[test]
+    fn highlight_task_renders_both_dates_in_completed_then_created_order() {
+        let mut task = Task::default();
+        task.completed_date = Some(Date {
+            year: 2024,
+            month: 1,
+            day: 2,
+        });
+        task.created = Some(Date {
+            year: 2024,
+            month: 1,
+            day: 1,
+        });
+        task.summary = "Call mom".to_string();
+        let line = highlight_task(&task);
+        assert_eq!(spans_text(&line), "2024-01-02 2024-01-01 Call mom");
+    }
+}
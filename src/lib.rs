@@ -1,13 +1,17 @@
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
 use std::{
     fs,
     io::Write,
     path::{Path, PathBuf},
     str::FromStr,
+    sync::mpsc::Sender,
 };
 
 #[derive(Debug)]
 pub enum Error {
     Io(std::io::Error),
+    Parse(String),
+    Watch(notify::Error),
 }
 
 impl From<std::io::Error> for Error {
@@ -16,6 +20,12 @@ impl From<std::io::Error> for Error {
     }
 }
 
+impl From<notify::Error> for Error {
+    fn from(e: notify::Error) -> Self {
+        Error::Watch(e)
+    }
+}
+
 #[derive(Debug)]
 pub struct TodoFile {
     pub path: PathBuf,
@@ -47,39 +57,305 @@ impl TodoFile {
         }
         Ok(())
     }
+
+    /// Spawns a background watcher that forwards change events for
+    /// `self.path` on `tx`, so the caller can react to external edits (e.g.
+    /// the same file being edited by another program). The returned
+    /// watcher must be kept alive for as long as watching should continue.
+    pub fn watch(
+        &self,
+        tx: Sender<notify::Result<notify::Event>>,
+    ) -> Result<RecommendedWatcher, Error> {
+        let mut watcher = notify::recommended_watcher(move |res| {
+            let _ = tx.send(res);
+        })?;
+        watcher.watch(&self.path, RecursiveMode::NonRecursive)?;
+        Ok(watcher)
+    }
+}
+
+/// A calendar date as used by the `YYYY-MM-DD` fields in todo.txt (completion
+/// and creation dates). Kept deliberately simple: no calendar validation
+/// beyond the field widths, since todo.txt files are plain text round-tripped
+/// as-is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Date {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+}
+
+impl FromStr for Date {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.splitn(3, '-');
+        let (Some(y), Some(m), Some(d)) = (parts.next(), parts.next(), parts.next()) else {
+            return Err(Error::Parse(format!("invalid date: {s}")));
+        };
+        let field_ok = |f: &str, width: usize| f.len() == width && f.bytes().all(|b| b.is_ascii_digit());
+        if !(field_ok(y, 4) && field_ok(m, 2) && field_ok(d, 2)) {
+            return Err(Error::Parse(format!("invalid date: {s}")));
+        }
+        let (Ok(year), Ok(month), Ok(day)) = (y.parse(), m.parse(), d.parse()) else {
+            return Err(Error::Parse(format!("invalid date: {s}")));
+        };
+        Ok(Date { year, month, day })
+    }
+}
+
+impl ToString for Date {
+    fn to_string(&self) -> String {
+        format!("{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
 }
 
 #[derive(Debug, Default, Clone)]
 pub struct Task {
-    pub summary: String,
     pub completed: bool,
+    pub priority: Option<char>,
+    pub completed_date: Option<Date>,
+    pub created: Option<Date>,
+    /// The free-text body, as originally written (minus the leading
+    /// completion marker, priority and dates). Keeping this around verbatim
+    /// is what lets `ToString` round-trip a file without reshuffling words.
+    pub summary: String,
+    pub projects: Vec<String>,
+    pub contexts: Vec<String>,
+    pub tags: Vec<(String, String)>,
+}
+
+impl Task {
+    /// Re-scans `summary` and refreshes `projects`, `contexts` and `tags`.
+    /// Call this after editing `summary` by hand so the structured fields
+    /// stay in sync for filtering/sorting.
+    pub fn reparse_summary(&mut self) {
+        let (projects, contexts, tags) = scan_words(&self.summary);
+        self.projects = projects;
+        self.contexts = contexts;
+        self.tags = tags;
+    }
+}
+
+/// Recognizes a `key:value` tag word, rejecting things that merely contain a
+/// colon but aren't one: a URL like `http://example.com` (value starting
+/// with `/`) or a time like `10:30` (key not starting with a letter).
+pub fn parse_tag(word: &str) -> Option<(&str, &str)> {
+    let (key, value) = word.split_once(':')?;
+    if key.is_empty() || value.is_empty() {
+        return None;
+    }
+    if !key.starts_with(|c: char| c.is_alphabetic()) {
+        return None;
+    }
+    if value.starts_with('/') {
+        return None;
+    }
+    Some((key, value))
+}
+
+/// Pulls `+project`, `@context` and `key:value` tokens out of a body of text,
+/// in the order they appear.
+fn scan_words(body: &str) -> (Vec<String>, Vec<String>, Vec<(String, String)>) {
+    let mut projects = Vec::new();
+    let mut contexts = Vec::new();
+    let mut tags = Vec::new();
+    for word in body.split_whitespace() {
+        if let Some(project) = word.strip_prefix('+') {
+            if !project.is_empty() {
+                projects.push(project.to_string());
+            }
+        } else if let Some(context) = word.strip_prefix('@') {
+            if !context.is_empty() {
+                contexts.push(context.to_string());
+            }
+        } else if let Some((key, value)) = parse_tag(word) {
+            tags.push((key.to_string(), value.to_string()));
+        }
+    }
+    (projects, contexts, tags)
 }
 
 impl FromStr for Task {
     type Err = Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let s = s.trim();
-        if s.starts_with("x") {
-            Ok(Task {
-                summary: s[1..].trim().to_string(),
-                completed: true,
-            })
+        let mut rest = s.trim();
+
+        let completed = if let Some(stripped) = rest.strip_prefix("x ") {
+            rest = stripped.trim_start();
+            true
         } else {
-            Ok(Task {
-                summary: s.to_string(),
-                completed: false,
-            })
+            false
+        };
+
+        let priority = if rest.len() >= 4
+            && rest.starts_with('(')
+            && rest.as_bytes()[1].is_ascii_uppercase()
+            && rest.as_bytes()[2] == b')'
+            && rest.as_bytes()[3] == b' '
+        {
+            let c = rest[1..2].chars().next();
+            rest = rest[3..].trim_start();
+            c
+        } else {
+            None
+        };
+
+        // A completion date is only valid when followed by a creation date,
+        // per the todo.txt spec; otherwise the single date found is the
+        // creation date.
+        let mut dates = Vec::new();
+        let mut lookahead = rest;
+        for _ in 0..2 {
+            let Some((candidate, after)) = lookahead.split_once(' ') else {
+                break;
+            };
+            if let Ok(date) = candidate.parse::<Date>() {
+                dates.push(date);
+                lookahead = after.trim_start();
+            } else {
+                break;
+            }
+        }
+        let (completed_date, created) = match dates.len() {
+            2 => (Some(dates[0]), Some(dates[1])),
+            1 => (None, Some(dates[0])),
+            _ => (None, None),
+        };
+        if !dates.is_empty() {
+            rest = lookahead;
         }
+
+        let summary = rest.to_string();
+        let (projects, contexts, tags) = scan_words(&summary);
+
+        Ok(Task {
+            completed,
+            priority,
+            completed_date,
+            created,
+            summary,
+            projects,
+            contexts,
+            tags,
+        })
     }
 }
 
 impl ToString for Task {
     fn to_string(&self) -> String {
+        let mut out = String::new();
         if self.completed {
-            format!("x {}", self.summary)
-        } else {
-            format!("  {}", self.summary)
+            out.push_str("x ");
+        }
+        if let Some(priority) = self.priority {
+            out.push_str(&format!("({priority}) "));
+        }
+        if let Some(completed_date) = self.completed_date {
+            out.push_str(&completed_date.to_string());
+            out.push(' ');
+        }
+        if let Some(created) = self.created {
+            out.push_str(&created.to_string());
+            out.push(' ');
         }
+        out.push_str(&self.summary);
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_plain_task() {
+        let task: Task = "Buy milk".parse().unwrap();
+        assert!(!task.completed);
+        assert_eq!(task.priority, None);
+        assert_eq!(task.created, None);
+        assert_eq!(task.summary, "Buy milk");
+    }
+
+    #[test]
+    fn parses_completion_marker() {
+        let task: Task = "x Buy milk".parse().unwrap();
+        assert!(task.completed);
+        assert_eq!(task.summary, "Buy milk");
+    }
+
+    #[test]
+    fn single_date_is_creation_date_not_completion_date() {
+        // Per the todo.txt spec, a completion date is only valid when
+        // immediately followed by a creation date.
+        let task: Task = "2024-01-02 Buy milk".parse().unwrap();
+        assert_eq!(task.completed_date, None);
+        assert_eq!(
+            task.created,
+            Some(Date {
+                year: 2024,
+                month: 1,
+                day: 2
+            })
+        );
+        assert_eq!(task.summary, "Buy milk");
+    }
+
+    #[test]
+    fn completion_date_requires_creation_date() {
+        let task: Task = "x 2024-01-02 2024-01-01 Buy milk".parse().unwrap();
+        assert_eq!(
+            task.completed_date,
+            Some(Date {
+                year: 2024,
+                month: 1,
+                day: 2
+            })
+        );
+        assert_eq!(
+            task.created,
+            Some(Date {
+                year: 2024,
+                month: 1,
+                day: 1
+            })
+        );
+        assert_eq!(task.summary, "Buy milk");
+    }
+
+    #[test]
+    fn parses_priority_projects_contexts_and_tags() {
+        let task: Task = "(A) Call mom +Family @phone due:2024-01-01".parse().unwrap();
+        assert_eq!(task.priority, Some('A'));
+        assert_eq!(task.projects, vec!["Family".to_string()]);
+        assert_eq!(task.contexts, vec!["phone".to_string()]);
+        assert_eq!(
+            task.tags,
+            vec![("due".to_string(), "2024-01-01".to_string())]
+        );
+    }
+
+    #[test]
+    fn lowercase_priority_is_not_a_priority() {
+        let task: Task = "(a) not a priority".parse().unwrap();
+        assert_eq!(task.priority, None);
+        assert_eq!(task.summary, "(a) not a priority");
+    }
+
+    #[test]
+    fn to_string_round_trips_without_reordering_words() {
+        let line = "x 2024-01-02 2024-01-01 (A) looks like a priority but isn't +z @a due:later";
+        // The `(A)` here is part of the body, not the leading priority slot,
+        // so it must stay exactly where it was typed.
+        let task: Task = line.parse().unwrap();
+        assert_eq!(task.priority, None);
+        assert_eq!(task.to_string(), line);
+    }
+
+    #[test]
+    fn tags_reject_urls_and_times() {
+        let task: Task = "check http://example.com at 10:30".parse().unwrap();
+        assert!(task.tags.is_empty());
     }
 }
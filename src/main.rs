@@ -11,9 +11,15 @@ use ratatui::{
     widgets::{Paragraph, Wrap},
     Frame,
 };
-use std::{io::stdout, path::PathBuf};
+use std::{collections::HashMap, io::stdout, path::PathBuf, sync::mpsc};
 use tplan::{Error, Task, TodoFile};
 
+mod actions;
+mod highlight;
+mod text;
+use actions::{key_code_to_string, load_actions, load_keymap, Action, Keymap};
+use highlight::highlight_task;
+
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 struct Cli {
@@ -31,6 +37,25 @@ enum Mode {
         cursor: usize,
         text: String,
     },
+    Command {
+        buf: String,
+        cursor: usize,
+    },
+}
+
+/// A `:filter` restriction, hiding tasks that don't match from the list.
+enum Filter {
+    Project(String),
+    Context(String),
+}
+
+impl Filter {
+    fn matches(&self, task: &Task) -> bool {
+        match self {
+            Filter::Project(p) => task.projects.iter().any(|proj| proj == p),
+            Filter::Context(c) => task.contexts.iter().any(|ctx| ctx == c),
+        }
+    }
 }
 
 struct App {
@@ -38,6 +63,16 @@ struct App {
     mode: Mode,
     pub is_running: bool,
     pub is_dirty: bool,
+    filter: Option<Filter>,
+    message: Option<String>,
+    /// Set after a quit press while `is_dirty`, so the *next* quit press
+    /// actually exits instead of showing the warning again.
+    quit_pending: bool,
+    /// Index of the first task drawn, kept in sync with the selection by
+    /// `sync_scroll`.
+    row_offset: usize,
+    /// Number of task rows the last `draw()` had room for.
+    visible_rows: usize,
 }
 
 impl App {
@@ -48,48 +83,215 @@ impl App {
             mode: Mode::View,
             is_running: true,
             is_dirty: false,
+            filter: None,
+            message: None,
+            quit_pending: false,
+            row_offset: 0,
+            visible_rows: 0,
         })
     }
 
+    /// Unconditionally exits, bypassing the unsaved-changes guard. Used by
+    /// `:q!` and by `:q` once there's nothing left to lose.
     fn quit(&mut self) {
         self.is_running = false;
     }
 
+    /// The `q`/`Esc` keybinding: warns once if there are unsaved changes and
+    /// only exits on the next consecutive press.
+    fn quit_guarded(&mut self) {
+        if self.is_dirty && !self.quit_pending {
+            self.quit_pending = true;
+            self.message = Some("Unsaved changes — press q again to quit".to_string());
+        } else {
+            self.quit();
+        }
+    }
+
+    /// Indices into `todo_file.tasks` that pass the active `:filter`, in
+    /// order — i.e. the tasks `draw()` actually renders a row for. Movement
+    /// and scrolling are computed over this list rather than raw task
+    /// indices, since with a filter active one task index doesn't
+    /// necessarily correspond to one screen row.
+    fn visible_indices(&self) -> Vec<usize> {
+        match &self.filter {
+            None => (0..self.todo_file.tasks.len()).collect(),
+            Some(filter) => self
+                .todo_file
+                .tasks
+                .iter()
+                .enumerate()
+                .filter(|(_, t)| filter.matches(t))
+                .map(|(i, _)| i)
+                .collect(),
+        }
+    }
+
+    /// Keeps `row_offset` (itself a position within `visible_indices`, not
+    /// a raw task index) scrolled so that `position` stays on screen.
+    fn sync_scroll(&mut self, position: usize) {
+        if position < self.row_offset {
+            self.row_offset = position;
+        } else if self.visible_rows > 0 && position >= self.row_offset + self.visible_rows {
+            self.row_offset = position - self.visible_rows + 1;
+        }
+    }
+
+    fn enter_command(&mut self) {
+        self.message = None;
+        self.mode = Mode::Command {
+            buf: String::new(),
+            cursor: 0,
+        };
+    }
+
+    fn cancel_command(&mut self) {
+        if let Mode::Command { .. } = self.mode {
+            self.mode = Mode::View;
+        }
+    }
+
+    /// Parses and runs the command currently in the command line, then
+    /// returns to `View`.
+    fn run_command(&mut self) {
+        let Mode::Command { buf, .. } = &self.mode else {
+            return;
+        };
+        let cmd = buf.clone();
+        self.mode = Mode::View;
+        self.message = None;
+
+        let mut parts = cmd.trim().splitn(2, char::is_whitespace);
+        let name = parts.next().unwrap_or("");
+        let arg = parts.next().unwrap_or("").trim();
+
+        match name {
+            "w" => {
+                if let Err(e) = self.save() {
+                    self.message = Some(format!("Failed to save: {e:?}"));
+                }
+            }
+            "q" => {
+                if self.is_dirty {
+                    self.message = Some("Unsaved changes — use :q! to discard".to_string());
+                } else {
+                    self.quit();
+                }
+            }
+            "q!" => self.quit(),
+            "sort" => self.sort_tasks(arg),
+            "filter" => self.set_filter(arg),
+            "" => {}
+            _ => self.message = Some(format!("Unknown command: {name}")),
+        }
+    }
+
+    fn sort_tasks(&mut self, by: &str) {
+        match by {
+            "pri" => self
+                .todo_file
+                .tasks
+                .sort_by_key(|t| t.priority.unwrap_or('~')),
+            // `Option<&String>`'s `Ord` would otherwise put unset projects
+            // first; sort them last instead, matching the `pri` convention
+            // above (`unwrap_or('~')`) of unset values sorting to the end.
+            "proj" => self.todo_file.tasks.sort_by_key(|t| {
+                (t.projects.first().is_none(), t.projects.first().cloned())
+            }),
+            _ => {
+                self.message = Some(format!("Unknown sort key: {by}"));
+                return;
+            }
+        }
+        self.is_dirty = true;
+    }
+
+    fn set_filter(&mut self, arg: &str) {
+        self.filter = if let Some(project) = arg.strip_prefix('+') {
+            Some(Filter::Project(project.to_string()))
+        } else if let Some(context) = arg.strip_prefix('@') {
+            Some(Filter::Context(context.to_string()))
+        } else if arg.is_empty() {
+            None
+        } else {
+            self.message = Some(format!("Unknown filter: {arg}"));
+            return;
+        };
+    }
+
     fn select_first(&mut self) {
-        self.mode = Mode::Select(0);
+        let visible = self.visible_indices();
+        if let Some(&first) = visible.first() {
+            self.mode = Mode::Select(first);
+            self.sync_scroll(0);
+        }
     }
 
     fn select_last(&mut self) {
-        self.mode = Mode::Select(self.todo_file.tasks.len() - 1);
+        let visible = self.visible_indices();
+        if let Some(&last) = visible.last() {
+            self.mode = Mode::Select(last);
+            self.sync_scroll(visible.len() - 1);
+        }
     }
 
     fn select_next(&mut self) {
-        if let Mode::Select(i) = &mut self.mode {
-            *i = (*i + 1).min(self.todo_file.tasks.len() - 1);
-        } else {
-            self.mode = Mode::Select(0);
-        }
+        let visible = self.visible_indices();
+        let Some(&first) = visible.first() else {
+            return;
+        };
+        let position = match self.mode {
+            Mode::Select(i) => visible
+                .iter()
+                .position(|&v| v == i)
+                .map(|p| (p + 1).min(visible.len() - 1))
+                .unwrap_or(0),
+            _ => 0,
+        };
+        self.mode = Mode::Select(*visible.get(position).unwrap_or(&first));
+        self.sync_scroll(position);
     }
 
     fn select_prev(&mut self) {
-        if let Mode::Select(i) = &mut self.mode {
-            *i = i.saturating_sub(1);
-        } else {
-            self.mode = Mode::Select(0);
-        }
+        let visible = self.visible_indices();
+        let Some(&first) = visible.first() else {
+            return;
+        };
+        let position = match self.mode {
+            Mode::Select(i) => visible
+                .iter()
+                .position(|&v| v == i)
+                .map(|p| p.saturating_sub(1))
+                .unwrap_or(0),
+            _ => 0,
+        };
+        self.mode = Mode::Select(*visible.get(position).unwrap_or(&first));
+        self.sync_scroll(position);
     }
 
     fn complete_selected(&mut self) {
         if let Mode::Select(i) = self.mode {
-            self.todo_file.tasks[i].completed = !self.todo_file.tasks[i].completed;
-            self.is_dirty = true;
+            if let Some(task) = self.todo_file.tasks.get_mut(i) {
+                task.completed = !task.completed;
+                self.is_dirty = true;
+            }
         }
     }
 
+    /// Deletes the selected task, then re-clamps `Mode::Select` to the new
+    /// (shorter) task list so a stale index can't panic on the next
+    /// `x`/`space`/`c`/`e`/`a` press.
     fn delete_selected(&mut self) {
         if let Mode::Select(i) = self.mode {
+            if i >= self.todo_file.tasks.len() {
+                return;
+            }
             self.todo_file.tasks.remove(i);
             self.is_dirty = true;
+            self.mode = match self.todo_file.tasks.len() {
+                0 => Mode::View,
+                len => Mode::Select(i.min(len - 1)),
+            };
         }
     }
 
@@ -99,15 +301,27 @@ impl App {
         } else {
             0
         };
+        if index >= self.todo_file.tasks.len() {
+            return;
+        }
+        // A filter can hide the very task being edited (or any task, once
+        // editing changes its +project/@context tokens), so drop it rather
+        // than leave the editor with no visible row to render into.
+        self.filter = None;
         let text = self.todo_file.tasks[index].summary.clone();
         self.mode = Mode::Edit {
             item: index,
-            cursor: text.len(),
+            cursor: text::char_len(&text),
             text,
         };
     }
 
+    /// Inserts a blank task at `index` and enters `Mode::Edit` on it.
+    /// `index` is clamped to `tasks.len()`, so an index past the end of the
+    /// list appends the new task instead of panicking.
     fn add_edit_task(&mut self, index: usize) {
+        let index = index.min(self.todo_file.tasks.len());
+        self.filter = None;
         self.todo_file.tasks.insert(index, Task::default());
         self.mode = Mode::Edit {
             item: index,
@@ -140,9 +354,41 @@ impl App {
         Ok(())
     }
 
+    /// Called when the watched todo.txt file changes on disk. `save()`
+    /// rewrites the file in place, which the watcher reports the same way
+    /// as an external edit — so this first checks whether the new contents
+    /// actually differ from what's already in memory (i.e. our own write)
+    /// and does nothing if they match. Otherwise, if there are no unsaved
+    /// in-memory edits, the new contents are loaded in; if there are, the
+    /// reload is skipped and a conflict is reported via the status line so
+    /// the unsaved edits are never silently clobbered. An in-progress
+    /// `Edit`/`Command` buffer counts as unsaved too, even before `is_dirty`
+    /// would otherwise be set.
+    fn reload(&mut self) -> Result<(), Error> {
+        let on_disk = TodoFile::load(&self.todo_file.path)?;
+        if serialize_tasks(&on_disk.tasks) == serialize_tasks(&self.todo_file.tasks) {
+            return Ok(());
+        }
+        let has_unsaved_edits =
+            self.is_dirty || matches!(self.mode, Mode::Edit { .. } | Mode::Command { .. });
+        if has_unsaved_edits {
+            self.message = Some(
+                "File changed on disk — not reloaded over unsaved changes (:w overwrites it)"
+                    .to_string(),
+            );
+            return Ok(());
+        }
+        self.todo_file = on_disk;
+        self.mode = Mode::View;
+        self.row_offset = 0;
+        Ok(())
+    }
+
     fn save_edit(&mut self) {
         if let Mode::Edit { item, text, .. } = &self.mode {
-            self.todo_file.tasks[*item].summary = text.clone();
+            let task = &mut self.todo_file.tasks[*item];
+            task.summary = text.clone();
+            task.reparse_summary();
             self.mode = Mode::Select(*item);
         }
         self.is_dirty = true;
@@ -155,6 +401,12 @@ impl App {
     }
 }
 
+/// Renders tasks the same way `TodoFile::save` would write them, so two
+/// in-memory task lists can be compared for "would produce the same file".
+fn serialize_tasks(tasks: &[Task]) -> String {
+    tasks.iter().map(Task::to_string).collect::<Vec<_>>().join("\n")
+}
+
 fn main() -> Result<(), Error> {
     let cli = Cli::parse();
 
@@ -165,90 +417,80 @@ fn main() -> Result<(), Error> {
     });
     let mut app = App::load(file_path)?;
 
+    let (fs_tx, fs_rx) = mpsc::channel();
+    // Kept alive for the rest of `main` so the watch doesn't stop firing.
+    let _watcher = app.todo_file.watch(fs_tx)?;
+
     stdout().execute(EnterAlternateScreen)?;
     enable_raw_mode()?;
     let mut terminal = Terminal::new(CrosstermBackend::new(stdout()))?;
     terminal.clear()?;
 
-    run(&mut terminal, &mut app)?;
+    let actions = load_actions();
+    let keymap = load_keymap();
+    run(&mut terminal, &mut app, &actions, &keymap, &fs_rx)?;
 
     stdout().execute(LeaveAlternateScreen)?;
     disable_raw_mode()?;
     Ok(())
 }
 
-fn run<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> Result<(), Error> {
+fn dispatch(
+    mode_keymap: &HashMap<String, String>,
+    actions: &HashMap<&'static str, Action>,
+    key: KeyCode,
+    app: &mut App,
+) -> bool {
+    let action_name = key_code_to_string(key).and_then(|key_str| mode_keymap.get(&key_str));
+    if action_name.map(String::as_str) != Some("quit") {
+        app.quit_pending = false;
+    }
+    let Some(action_name) = action_name else {
+        return false;
+    };
+    let Some(action) = actions.get(action_name.as_str()) else {
+        return false;
+    };
+    action(app);
+    true
+}
+
+fn run<B: Backend>(
+    terminal: &mut Terminal<B>,
+    app: &mut App,
+    actions: &HashMap<&'static str, Action>,
+    keymap: &Keymap,
+    fs_rx: &mpsc::Receiver<notify::Result<notify::Event>>,
+) -> Result<(), Error> {
     terminal.draw(|f| draw(f, app))?;
     while app.is_running {
         let mut redraw = false;
+        // There's no single source we can block on (key events come from
+        // crossterm, file events from a channel fed by the `notify`
+        // watcher thread), so poll the key source with a short timeout and
+        // take at most one pending file event from the channel each pass
+        // instead of a true select.
+        if let Ok(Ok(event)) = fs_rx.try_recv() {
+            if event.kind.is_modify() || event.kind.is_create() {
+                app.reload()?;
+                redraw = true;
+            }
+        }
         if event::poll(std::time::Duration::from_millis(1))? {
             if let event::Event::Key(key) = event::read()? {
+                // Any keypress clears a stale transient message (a save
+                // error, an unknown-command notice, the quit warning, ...)
+                // so the status bar underneath it isn't hidden for the rest
+                // of the run. Actions that want to show a fresh message
+                // (like `quit_guarded`) set it again after this.
+                app.message = None;
                 match &mut app.mode {
-                    Mode::View => match key.code {
-                        KeyCode::Esc | KeyCode::Char('q') => app.quit(),
-                        KeyCode::Char('a') => {
-                            app.append_task();
-                            redraw = true;
-                        }
-                        KeyCode::Char('g') | KeyCode::Home => {
-                            app.select_first();
-                            redraw = true;
-                        }
-                        KeyCode::Char('G') | KeyCode::End => {
-                            app.select_last();
-                            redraw = true;
-                        }
-                        KeyCode::Char('j') | KeyCode::Down => {
-                            app.select_next();
-                            redraw = true;
-                        }
-                        KeyCode::Char('k') | KeyCode::Up => {
-                            app.select_prev();
-                            redraw = true;
-                        }
-                        _ => {}
-                    },
-                    Mode::Select(_) => match key.code {
-                        KeyCode::Char('g') | KeyCode::Home => {
-                            app.select_first();
-                            redraw = true;
-                        }
-                        KeyCode::Char('G') | KeyCode::End => {
-                            app.select_last();
-                            redraw = true;
-                        }
-                        KeyCode::Char('j') | KeyCode::Down => {
-                            app.select_next();
-                            redraw = true;
-                        }
-                        KeyCode::Char('k') | KeyCode::Up => {
-                            app.select_prev();
-                            redraw = true;
-                        }
-                        KeyCode::Char(' ') | KeyCode::Enter => {
-                            app.complete_selected();
-                            redraw = true;
-                        }
-                        KeyCode::Char('x') | KeyCode::Delete => {
-                            app.delete_selected();
-                            // TOFIX: Index out of bounds
-                            redraw = true;
-                        }
-                        KeyCode::Char('i') => {
-                            app.insert_task();
-                            redraw = true;
-                        }
-                        KeyCode::Char('a') => {
-                            app.append_task();
-                            redraw = true;
-                        }
-                        KeyCode::Char('c') | KeyCode::Char('e') => {
-                            app.edit_task();
-                            redraw = true;
-                        }
-                        KeyCode::Char('q') => app.quit(),
-                        _ => {}
-                    },
+                    Mode::View => {
+                        redraw = dispatch(&keymap.view, actions, key.code, app);
+                    }
+                    Mode::Select(_) => {
+                        redraw = dispatch(&keymap.select, actions, key.code, app);
+                    }
                     Mode::Edit {
                         item: _,
                         cursor,
@@ -258,48 +500,26 @@ fn run<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> Result<(), Erro
                             app.cancel_edit();
                             redraw = true;
                         }
-                        KeyCode::Char(c) => {
-                            text.insert(*cursor, c);
-                            *cursor += 1;
+                        KeyCode::Enter => {
+                            app.save_edit();
                             redraw = true;
                         }
-                        KeyCode::Backspace => {
-                            if *cursor > 0 {
-                                *cursor -= 1;
-                                text.remove(*cursor);
-                                redraw = true;
-                            }
-                        }
-                        KeyCode::Delete => {
-                            if *cursor < text.len() {
-                                text.remove(*cursor);
-                                redraw = true;
-                            }
-                        }
-                        KeyCode::Left => {
-                            if *cursor > 0 {
-                                *cursor -= 1;
-                                redraw = true;
-                            }
-                        }
-                        KeyCode::Right => {
-                            if *cursor < text.len() {
-                                *cursor += 1;
-                                redraw = true;
-                            }
+                        _ => redraw = text::handle_text_key(key, text, cursor),
+                    },
+                    Mode::Command { cursor, buf } => match key.code {
+                        KeyCode::Esc => {
+                            app.cancel_command();
+                            redraw = true;
                         }
                         KeyCode::Enter => {
-                            app.save_edit();
+                            app.run_command();
                             redraw = true;
                         }
-                        _ => {}
+                        _ => redraw = text::handle_text_key(key, buf, cursor),
                     },
                 }
             }
         }
-        if app.is_dirty {
-            app.save()?;
-        }
         if redraw {
             terminal.draw(|f| draw(f, app))?;
         }
@@ -310,29 +530,35 @@ fn run<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> Result<(), Erro
 fn draw(frame: &mut Frame, app: &mut App) {
     let area = frame.size();
     frame.render_widget(Paragraph::new("TPLAN").blue().bold(), area);
+    // One row for the title, one for the status/command line at the bottom.
+    app.visible_rows = area.height.saturating_sub(2) as usize;
     let mut line_area = Rect { height: 1, ..area };
     line_area.x += 2;
     line_area.width -= 2;
+    // `row_offset` counts *visible* (filter-matching) rows, not raw task
+    // indices, so it lines up with the positions `select_next`/`select_prev`
+    // scroll against.
+    let mut visible_pos = 0usize;
     for (i, task) in app.todo_file.tasks.iter().enumerate() {
+        if let Some(filter) = &app.filter {
+            if !filter.matches(task) {
+                continue;
+            }
+        }
+        let position = visible_pos;
+        visible_pos += 1;
+        if position < app.row_offset {
+            continue;
+        }
         line_area.y += 1;
-        if line_area.bottom() > area.bottom() {
+        if line_area.bottom() > area.bottom().saturating_sub(1) {
             break;
         }
-        let paragraph = if task.completed {
-            Paragraph::new(task.summary.clone())
-                .wrap(Wrap { trim: true })
-                .reset()
-                .dim()
-                .italic()
-                .crossed_out()
-        } else {
-            Paragraph::new(task.summary.clone())
-                .wrap(Wrap { trim: true })
-                .reset()
-                .white()
-        };
+        let paragraph = Paragraph::new(highlight_task(task)).wrap(Wrap { trim: true });
         if Mode::Select(i) == app.mode {
-            frame.render_widget(paragraph.black().on_yellow(), line_area);
+            // Only patch in the background so the per-span token colors
+            // still show through the selection highlight.
+            frame.render_widget(paragraph.on_yellow(), line_area);
             continue;
         } else if let Mode::Edit { item, text, cursor } = &app.mode {
             if *item == i {
@@ -346,4 +572,33 @@ fn draw(frame: &mut Frame, app: &mut App) {
         }
         frame.render_widget(paragraph, line_area);
     }
+
+    let mut bottom_area = Rect { height: 1, ..area };
+    bottom_area.y = area.bottom() - 1;
+    if let Mode::Command { buf, cursor } = &app.mode {
+        frame.set_cursor(bottom_area.x + 1 + *cursor as u16, bottom_area.y);
+        frame.render_widget(Paragraph::new(format!(":{buf}")), bottom_area);
+    } else if let Some(message) = &app.message {
+        frame.render_widget(Paragraph::new(message.clone()).red(), bottom_area);
+    } else {
+        frame.render_widget(status_line(app).dim(), bottom_area);
+    }
+}
+
+fn status_line(app: &App) -> Paragraph<'static> {
+    let file_name = app
+        .todo_file
+        .path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let selected = match app.mode {
+        Mode::Select(i) => format!(" | {}/{}", i + 1, app.todo_file.tasks.len()),
+        _ => String::new(),
+    };
+    let modified = if app.is_dirty { " [+]" } else { "" };
+    Paragraph::new(format!(
+        "{file_name} | {} tasks{selected}{modified}",
+        app.todo_file.tasks.len()
+    ))
 }
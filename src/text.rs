@@ -0,0 +1,273 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+/// The three character classes vim distinguishes for word motions. The
+/// "long word" (WORD) variants collapse `Word` and `Punctuation` into one
+/// class so only whitespace separates tokens.
+#[derive(PartialEq, Eq, Clone, Copy)]
+enum CharClass {
+    Whitespace,
+    Word,
+    Punctuation,
+}
+
+fn classify(c: char, long: bool) -> CharClass {
+    if c.is_whitespace() {
+        CharClass::Whitespace
+    } else if long || c.is_alphanumeric() || c == '_' {
+        CharClass::Word
+    } else {
+        CharClass::Punctuation
+    }
+}
+
+pub fn char_len(text: &str) -> usize {
+    text.chars().count()
+}
+
+fn char_to_byte_index(text: &str, char_idx: usize) -> usize {
+    text.char_indices()
+        .nth(char_idx)
+        .map(|(b, _)| b)
+        .unwrap_or(text.len())
+}
+
+pub fn insert_char(text: &mut String, cursor: usize, c: char) {
+    let byte_idx = char_to_byte_index(text, cursor);
+    text.insert(byte_idx, c);
+}
+
+pub fn remove_char(text: &mut String, cursor: usize) {
+    let byte_idx = char_to_byte_index(text, cursor);
+    if byte_idx < text.len() {
+        text.remove(byte_idx);
+    }
+}
+
+/// `w`/`W`: the first character of the next token, skipping the rest of the
+/// current one and any whitespace in between.
+pub fn move_next_word_start(text: &str, cursor: usize, long: bool) -> usize {
+    let chars: Vec<char> = text.chars().collect();
+    let len = chars.len();
+    let mut i = cursor.min(len);
+    if i >= len {
+        return len;
+    }
+    let start_class = classify(chars[i], long);
+    while i < len && classify(chars[i], long) == start_class {
+        i += 1;
+    }
+    while i < len && classify(chars[i], long) == CharClass::Whitespace {
+        i += 1;
+    }
+    i
+}
+
+/// `b`/`B`: back over whitespace, then back to the first character of that
+/// token.
+pub fn move_prev_word_start(text: &str, cursor: usize, long: bool) -> usize {
+    let chars: Vec<char> = text.chars().collect();
+    let len = chars.len();
+    let mut i = cursor.min(len);
+    if i == 0 {
+        return 0;
+    }
+    i -= 1;
+    while i > 0 && classify(chars[i], long) == CharClass::Whitespace {
+        i -= 1;
+    }
+    if classify(chars[i], long) == CharClass::Whitespace {
+        return 0;
+    }
+    let class = classify(chars[i], long);
+    while i > 0 && classify(chars[i - 1], long) == class {
+        i -= 1;
+    }
+    i
+}
+
+/// `e`/`E`: forward over whitespace, then to the last character of the
+/// current or next token.
+pub fn move_next_word_end(text: &str, cursor: usize, long: bool) -> usize {
+    let chars: Vec<char> = text.chars().collect();
+    let len = chars.len();
+    if len == 0 {
+        return 0;
+    }
+    let mut i = (cursor + 1).min(len);
+    while i < len && classify(chars[i], long) == CharClass::Whitespace {
+        i += 1;
+    }
+    if i >= len {
+        return cursor.min(len - 1);
+    }
+    let class = classify(chars[i], long);
+    while i + 1 < len && classify(chars[i + 1], long) == class {
+        i += 1;
+    }
+    i
+}
+
+/// Handles the editing keys shared by `Mode::Edit` and `Mode::Command`:
+/// character insertion, deletion, and cursor motion (including word-wise and
+/// line-start/end motions). Returns whether the key was handled (and thus
+/// whether a redraw is needed).
+pub fn handle_text_key(key: KeyEvent, text: &mut String, cursor: &mut usize) -> bool {
+    let alt = key.modifiers.contains(KeyModifiers::ALT);
+    let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+    match key.code {
+        KeyCode::Char('w') if alt => {
+            *cursor = move_next_word_start(text, *cursor, false);
+            true
+        }
+        KeyCode::Char('W') if alt => {
+            *cursor = move_next_word_start(text, *cursor, true);
+            true
+        }
+        KeyCode::Char('b') if alt => {
+            *cursor = move_prev_word_start(text, *cursor, false);
+            true
+        }
+        KeyCode::Char('B') if alt => {
+            *cursor = move_prev_word_start(text, *cursor, true);
+            true
+        }
+        KeyCode::Char('e') if alt => {
+            *cursor = move_next_word_end(text, *cursor, false);
+            true
+        }
+        KeyCode::Char('E') if alt => {
+            *cursor = move_next_word_end(text, *cursor, true);
+            true
+        }
+        KeyCode::Char('a') if ctrl => {
+            *cursor = 0;
+            true
+        }
+        KeyCode::Char('e') if ctrl => {
+            *cursor = char_len(text);
+            true
+        }
+        KeyCode::Home => {
+            *cursor = 0;
+            true
+        }
+        KeyCode::End => {
+            *cursor = char_len(text);
+            true
+        }
+        KeyCode::Char(c) => {
+            insert_char(text, *cursor, c);
+            *cursor += 1;
+            true
+        }
+        KeyCode::Backspace => {
+            if *cursor > 0 {
+                *cursor -= 1;
+                remove_char(text, *cursor);
+                true
+            } else {
+                false
+            }
+        }
+        KeyCode::Delete => {
+            if *cursor < char_len(text) {
+                remove_char(text, *cursor);
+                true
+            } else {
+                false
+            }
+        }
+        KeyCode::Left => {
+            if *cursor > 0 {
+                *cursor -= 1;
+                true
+            } else {
+                false
+            }
+        }
+        KeyCode::Right => {
+            if *cursor < char_len(text) {
+                *cursor += 1;
+                true
+            } else {
+                false
+            }
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_line_motions_are_no_ops() {
+        assert_eq!(move_next_word_start("", 0, false), 0);
+        assert_eq!(move_prev_word_start("", 0, false), 0);
+        assert_eq!(move_next_word_end("", 0, false), 0);
+    }
+
+    #[test]
+    fn next_word_start_stops_at_punctuation_boundary() {
+        // `w` treats `.` as its own token, so it stops there rather than
+        // skipping straight to the next whitespace-separated word.
+        assert_eq!(move_next_word_start("foo.bar baz", 0, false), 3);
+    }
+
+    #[test]
+    fn next_word_start_word_variant_collapses_punctuation() {
+        // `W` only cares about whitespace, so "foo.bar" is one token.
+        assert_eq!(move_next_word_start("foo.bar baz", 0, true), 8);
+    }
+
+    #[test]
+    fn prev_word_start_word_variant_collapses_punctuation() {
+        assert_eq!(move_prev_word_start("foo.bar baz", 11, true), 8);
+    }
+
+    #[test]
+    fn next_word_end_stops_at_punctuation_boundary() {
+        assert_eq!(move_next_word_end("foo.bar baz", 0, false), 2);
+    }
+
+    #[test]
+    fn next_word_end_word_variant_collapses_punctuation() {
+        assert_eq!(move_next_word_end("foo.bar baz", 0, true), 6);
+    }
+
+    #[test]
+    fn next_word_end_clamps_at_last_char() {
+        let text = "foo";
+        assert_eq!(move_next_word_end(text, 2, false), 2);
+    }
+
+    #[test]
+    fn motions_are_clamped_to_bounds() {
+        assert_eq!(move_next_word_start("foo", 100, false), 3);
+        assert_eq!(move_prev_word_start("foo", 100, false), 0);
+    }
+
+    #[test]
+    fn char_len_counts_chars_not_bytes() {
+        assert_eq!(char_len("héllo"), 5);
+        assert_ne!(char_len("héllo"), "héllo".len());
+    }
+
+    #[test]
+    fn insert_and_remove_char_are_char_aware_on_multibyte_utf8() {
+        let mut text = "héllo".to_string();
+        insert_char(&mut text, 1, 'X');
+        assert_eq!(text, "hXéllo");
+        // Index 2 is 'é', not a byte offset into its 2-byte encoding.
+        remove_char(&mut text, 2);
+        assert_eq!(text, "hXllo");
+    }
+
+    #[test]
+    fn word_motions_treat_multibyte_chars_as_word_chars() {
+        let text = "héllo wörld";
+        assert_eq!(move_next_word_start(text, 0, false), 6);
+        assert_eq!(move_prev_word_start(text, char_len(text), false), 6);
+    }
+}